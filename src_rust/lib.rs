@@ -1,54 +1,97 @@
-use fasteval::{Compiler, Evaler, Slab, Parser};
 use numpy::PyReadonlyArray1;
 use pyo3::prelude::*;
 use pyo3::types::PyModule;
 use rayon::prelude::*;
 use regex::Regex;
 use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, Read};
 use std::sync::OnceLock;
 
 static RE_INLINE: OnceLock<Regex> = OnceLock::new();
 static RE_RC_PRIMARY: OnceLock<Regex> = OnceLock::new();
 static RE_RC_SECONDARY: OnceLock<Regex> = OnceLock::new();
 static RE_ANY_TAG: OnceLock<Regex> = OnceLock::new();
+static RE_SYNOPTSET: OnceLock<Regex> = OnceLock::new();
+static RE_DLGTAB: OnceLock<Regex> = OnceLock::new();
+static RE_HLINE: OnceLock<Regex> = OnceLock::new();
+static RE_COL_SPACE: OnceLock<Regex> = OnceLock::new();
 
 const PAR_SORT_THRESHOLD: usize = 2_500;
 const PAR_FILTER_THRESHOLD: usize = 5_000;
+const PAR_GROUP_THRESHOLD: usize = 1_000;
 const MAX_FILTER_EXPR_LEN: usize = 1000;
 
+// Stata's numeric missing values `.`, `.a`, `.b`, ... `.z` are 27 distinct codes that
+// sort above every real number and order among themselves as `. < .a < .b < ... < .z`.
+// We encode them as a band of sentinel doubles just below f64::MAX so the Python side
+// can produce them with `stata_missing_value` and every comparison/sort/filter path
+// here recognizes them without a parallel tag array. Plain `NaN` (the generic sysmiss
+// produced by ordinary float arithmetic) is treated as tag 0, i.e. the same rank as `.`.
+const MISSING_BASE: f64 = 8.988_465_674_311_579e307;
+const MISSING_TAG_COUNT: u8 = 27; // '.' plus '.a'..'.z'
+const MISSING_STEP: f64 = (f64::MAX - MISSING_BASE) / MISSING_TAG_COUNT as f64;
+
+fn missing_sentinel(tag: u8) -> f64 {
+    MISSING_BASE + MISSING_STEP * tag as f64
+}
+
+fn missing_tag(v: f64) -> Option<u8> {
+    if v.is_nan() {
+        return Some(0);
+    }
+    if v >= MISSING_BASE {
+        let idx = ((v - MISSING_BASE) / MISSING_STEP).round() as i64;
+        return Some(idx.clamp(0, (MISSING_TAG_COUNT - 1) as i64) as u8);
+    }
+    None
+}
+
+fn is_missing(v: f64) -> bool {
+    missing_tag(v).is_some()
+}
+
 fn cmp_with_nulls(
     a: f64,
     b: f64,
     descending: bool,
     nulls_last: bool,
 ) -> Ordering {
-    let a_null = a.is_nan();
-    let b_null = b.is_nan();
+    let a_tag = missing_tag(a);
+    let b_tag = missing_tag(b);
 
-    if a_null || b_null {
-        if a_null && b_null {
-            return Ordering::Equal;
+    match (a_tag, b_tag) {
+        (Some(ta), Some(tb)) => {
+            // Relative order among missing codes always follows `. < .a < ... < .z`
+            // (reversed under `descending`, same as real values); `nulls_last` only
+            // controls where the whole missing block sits relative to real numbers.
+            let ord = ta.cmp(&tb);
+            if descending { ord.reverse() } else { ord }
         }
-        if nulls_last {
-            return if a_null { Ordering::Greater } else { Ordering::Less };
+        (Some(_), None) => {
+            if nulls_last { Ordering::Greater } else { Ordering::Less }
         }
-        return if a_null { Ordering::Less } else { Ordering::Greater };
-    }
-
-    if a < b {
-        if descending {
-            Ordering::Greater
-        } else {
-            Ordering::Less
+        (None, Some(_)) => {
+            if nulls_last { Ordering::Less } else { Ordering::Greater }
         }
-    } else if a > b {
-        if descending {
-            Ordering::Less
-        } else {
-            Ordering::Greater
+        (None, None) => {
+            if a < b {
+                if descending {
+                    Ordering::Greater
+                } else {
+                    Ordering::Less
+                }
+            } else if a > b {
+                if descending {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            } else {
+                Ordering::Equal
+            }
         }
-    } else {
-        Ordering::Equal
     }
 }
 
@@ -138,7 +181,11 @@ fn argsort_mixed_core(
                 return ord;
             }
         }
-        Ordering::Equal
+        // Break ties on original row index so the sort is stable even though it runs
+        // through `sort_unstable_by`/`par_sort_unstable_by` — callers like
+        // `group_aggregate_core` rely on rows within a tied group staying in dataset
+        // order so `first`/`last` are well-defined.
+        i.cmp(&j)
     };
 
     if row_count < PAR_SORT_THRESHOLD {
@@ -278,7 +325,7 @@ pub fn smcl_to_markdown(smcl: String) -> String {
     });
 
     let lines: Vec<&str> = smcl.lines().collect();
-    
+
     // Extract title sequentially (usually near top)
     let mut title = None;
     for line in &lines {
@@ -298,29 +345,7 @@ pub fn smcl_to_markdown(smcl: String) -> String {
             if trimmed.is_empty() || trimmed == "{smcl}" || trimmed.starts_with("{title:") {
                 return String::new();
             }
-
-            // Pass 1: Replace known tags with Markdown using Cow to avoid unnecessary allocations
-            let processed = re_inline.replace_all(trimmed, |caps: &regex::Captures| {
-                let tag = caps.get(1).map_or("", |m| m.as_str());
-                let content = caps.get(2).map_or("", |m| m.as_str());
-                
-                if tag.eq_ignore_ascii_case("bf") || tag.eq_ignore_ascii_case("strong") {
-                    format!("**{content}**")
-                } else if tag.eq_ignore_ascii_case("it") || tag.eq_ignore_ascii_case("em") {
-                    format!("*{content}*")
-                } else if tag.eq_ignore_ascii_case("cmd") || tag.eq_ignore_ascii_case("cmdab") || 
-                          tag.eq_ignore_ascii_case("code") || tag.eq_ignore_ascii_case("inp") || 
-                          tag.eq_ignore_ascii_case("input") || tag.eq_ignore_ascii_case("res") || 
-                          tag.eq_ignore_ascii_case("err") || tag.eq_ignore_ascii_case("txt") {
-                    format!("`{content}`")
-                } else {
-                    content.to_string()
-                }
-            });
-
-            // Pass 2: Strip all remaining tags (including p-tags and alignment tags)
-            let stripped = re_any.replace_all(&processed, "");
-            stripped.trim().to_string()
+            render_inline_tags(trimmed, re_inline, re_any)
         })
         .filter(|s| !s.is_empty())
         .collect();
@@ -332,25 +357,192 @@ pub fn smcl_to_markdown(smcl: String) -> String {
     }
 }
 
+// Shared by the flat renderer above and the table-aware renderer below: replace the
+// known inline tags (bold/italic/code) with Markdown, then strip whatever tags remain.
+fn render_inline_tags(s: &str, re_inline: &Regex, re_any: &Regex) -> String {
+    let processed = re_inline.replace_all(s, |caps: &regex::Captures| {
+        let tag = caps.get(1).map_or("", |m| m.as_str());
+        let content = caps.get(2).map_or("", |m| m.as_str());
+
+        if tag.eq_ignore_ascii_case("bf") || tag.eq_ignore_ascii_case("strong") {
+            format!("**{content}**")
+        } else if tag.eq_ignore_ascii_case("it") || tag.eq_ignore_ascii_case("em") {
+            format!("*{content}*")
+        } else if tag.eq_ignore_ascii_case("cmd") || tag.eq_ignore_ascii_case("cmdab") ||
+                  tag.eq_ignore_ascii_case("code") || tag.eq_ignore_ascii_case("inp") ||
+                  tag.eq_ignore_ascii_case("input") || tag.eq_ignore_ascii_case("res") ||
+                  tag.eq_ignore_ascii_case("err") || tag.eq_ignore_ascii_case("txt") {
+            format!("`{content}`")
+        } else {
+            content.to_string()
+        }
+    });
+
+    re_any.replace_all(&processed, "").trim().to_string()
+}
+
+/// Table-aware counterpart to `smcl_to_markdown`: recognizes the column-layout tags
+/// that make up Stata's regression/summary tables (`{synopt}`, `{synoptset}`,
+/// `{col N}`/`{space N}`, `{hline}`, `{dlgtab:Title}`) and renders them as
+/// GitHub-flavored Markdown tables instead of flattening them to run-together text.
+/// Everything else falls back to the same inline-tag handling as `smcl_to_markdown`.
 #[pyfunction]
-pub fn fast_scan_log(smcl_content: String, rc_default: i32) -> (String, String, Option<i32>) {
-    let re_rc_primary =
-        RE_RC_PRIMARY.get_or_init(|| Regex::new(r"\{search r\((\d+)\)").unwrap());
-    let re_rc_secondary =
-        RE_RC_SECONDARY.get_or_init(|| Regex::new(r"\br\((\d+)\);?").unwrap());
-    let re_any_tag = RE_ANY_TAG.get_or_init(|| Regex::new(r"\{[^}]*\}").unwrap());
+pub fn smcl_to_markdown_structured(smcl: String) -> String {
+    let re_inline = RE_INLINE.get_or_init(|| {
+        Regex::new(r"\{([a-zA-Z0-9_]+):([^}]*)\}").unwrap()
+    });
+    let re_any = RE_ANY_TAG.get_or_init(|| {
+        Regex::new(r"\{[^}]*\}").unwrap()
+    });
+    let re_synoptset =
+        RE_SYNOPTSET.get_or_init(|| Regex::new(r"^\{synoptset\s+\d+(?:\s+\w+)?\}").unwrap());
+    let re_dlgtab = RE_DLGTAB.get_or_init(|| Regex::new(r"^\{dlgtab:(.*?)\}$").unwrap());
+    let re_hline = RE_HLINE.get_or_init(|| Regex::new(r"^\{hline(?:\s+\d+)?\}$").unwrap());
+    let re_col_space = RE_COL_SPACE.get_or_init(|| Regex::new(r"\{(?:col|space)\s+\d+\}").unwrap());
 
-    let mut rc = None;
-    if let Some(caps) = re_rc_primary.captures_iter(&smcl_content).last() {
-        rc = caps.get(1).and_then(|m| m.as_str().parse::<i32>().ok());
+    let lines: Vec<&str> = smcl.lines().collect();
+
+    let mut title = None;
+    for line in &lines {
+        let trimmed = line.trim();
+        if trimmed.starts_with("{title:") {
+            if let Some(t) = trimmed.strip_prefix("{title:").and_then(|s| s.strip_suffix('}')) {
+                title = Some(t.to_string());
+                break;
+            }
+        }
     }
-    if rc.is_none() {
-        if let Some(caps) = re_rc_secondary.captures_iter(&smcl_content).last() {
-            rc = caps.get(1).and_then(|m| m.as_str().parse::<i32>().ok());
+
+    let mut out: Vec<String> = Vec::new();
+    let mut pending_header: Option<Vec<String>> = None;
+    let mut table_rows: Vec<Vec<String>> = Vec::new();
+
+    for line in &lines {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed == "{smcl}" || trimmed.starts_with("{title:") {
+            continue;
+        }
+        if re_synoptset.is_match(trimmed) {
+            continue;
+        }
+        if let Some(caps) = re_dlgtab.captures(trimmed) {
+            flush_table(&mut out, &mut pending_header, &mut table_rows);
+            let heading =
+                render_inline_tags(caps.get(1).map_or("", |m| m.as_str()), re_inline, re_any);
+            out.push(format!("### {heading}"));
+            continue;
+        }
+        if re_hline.is_match(trimmed) {
+            if pending_header.is_some() && table_rows.is_empty() {
+                // This hline closes off the header row; stay in the table for the body.
+                continue;
+            }
+            if !table_rows.is_empty() {
+                flush_table(&mut out, &mut pending_header, &mut table_rows);
+            }
+            continue;
+        }
+        if let Some((label, desc)) = split_balanced_tag(trimmed, "{synopt:") {
+            let label = render_inline_tags(label, re_inline, re_any);
+            let desc = render_inline_tags(desc, re_inline, re_any);
+            table_rows.push(vec![label, desc]);
+            continue;
+        }
+        if re_col_space.is_match(trimmed) {
+            let mut cells: Vec<String> = re_col_space
+                .split(trimmed)
+                .map(|seg| render_inline_tags(seg, re_inline, re_any))
+                .collect();
+            if cells.first().is_some_and(|c| c.is_empty()) {
+                cells.remove(0);
+            }
+            if pending_header.is_none() && table_rows.is_empty() {
+                pending_header = Some(cells);
+            } else {
+                table_rows.push(cells);
+            }
+            continue;
+        }
+
+        // A plain text line ends any table in progress and falls back to flat rendering.
+        flush_table(&mut out, &mut pending_header, &mut table_rows);
+        let rendered = render_inline_tags(trimmed, re_inline, re_any);
+        if !rendered.is_empty() {
+            out.push(rendered);
         }
     }
+    flush_table(&mut out, &mut pending_header, &mut table_rows);
+
+    let body = out.join("\n");
+    match title {
+        Some(t) => format!("## {t}\n\n{body}"),
+        None => body,
+    }
+}
+
+// Stata's own tags can nest (e.g. `{synopt:{opt noc:onstant}}desc`), so a non-greedy
+// regex for the outer tag's closing brace will stop at the first `}` it finds, which
+// may belong to an inner tag. Scan by brace depth instead to find the true match.
+fn split_balanced_tag<'a>(trimmed: &'a str, prefix: &str) -> Option<(&'a str, &'a str)> {
+    let rest = trimmed.strip_prefix(prefix)?;
+    let mut depth = 1i32;
+    let mut end = None;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    end = Some(i);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let end = end?;
+    Some((&rest[..end], &rest[end + 1..]))
+}
+
+fn flush_table(
+    out: &mut Vec<String>,
+    pending_header: &mut Option<Vec<String>>,
+    table_rows: &mut Vec<Vec<String>>,
+) {
+    if table_rows.is_empty() && pending_header.is_none() {
+        return;
+    }
+    let col_count = pending_header
+        .as_ref()
+        .map(|h| h.len())
+        .or_else(|| table_rows.first().map(|r| r.len()))
+        .unwrap_or(0);
+    if col_count == 0 {
+        table_rows.clear();
+        *pending_header = None;
+        return;
+    }
+
+    let header = pending_header
+        .take()
+        .unwrap_or_else(|| (1..=col_count).map(|i| format!("Col {i}")).collect());
+    out.push(format!("| {} |", header.join(" | ")));
+    out.push(format!("| {} |", vec!["---"; col_count].join(" | ")));
+    for row in table_rows.drain(..) {
+        let mut cells = row;
+        cells.resize(col_count, String::new());
+        out.push(format!("| {} |", cells.join(" | ")));
+    }
+}
+
+// Shared by `fast_scan_log` and `fast_scan_log_from_file`: given the already-decided rc
+// and the lines to search (the whole file for the former, a bounded trailing window for
+// the latter), walk backwards for the last `{err}` block and build its surrounding
+// context. Kept free of PyO3 types so both callers can hand it whatever slice of lines
+// they have on hand.
+fn assemble_error_and_context(lines: &[&str], rc: Option<i32>, rc_default: i32) -> (String, String) {
+    let re_any_tag = RE_ANY_TAG.get_or_init(|| Regex::new(r"\{[^}]*\}").unwrap());
 
-    let lines: Vec<&str> = smcl_content.lines().collect();
     let mut error_msg = format!("Stata error r({})", rc.unwrap_or(rc_default));
     let mut error_start_idx: Option<usize> = None;
 
@@ -385,170 +577,1076 @@ pub fn fast_scan_log(smcl_content: String, rc_default: i32) -> (String, String,
     };
     let context = lines[context_start..].join("\n");
 
-    (error_msg, context, rc)
+    (error_msg, context)
 }
 
 #[pyfunction]
-pub fn compute_filter_indices(
-    py: Python<'_>,
-    expr_str: String,
-    names: Vec<String>,
-    columns: Vec<Py<PyAny>>,
-    is_string: Vec<bool>,
-) -> PyResult<Vec<usize>> {
-    // Security: limit expression length
-    if expr_str.len() > MAX_FILTER_EXPR_LEN {
-        return Err(pyo3::exceptions::PyValueError::new_err("Filter expression too long"));
+pub fn fast_scan_log(smcl_content: String, rc_default: i32) -> (String, String, Option<i32>) {
+    let re_rc_primary =
+        RE_RC_PRIMARY.get_or_init(|| Regex::new(r"\{search r\((\d+)\)").unwrap());
+    let re_rc_secondary =
+        RE_RC_SECONDARY.get_or_init(|| Regex::new(r"\br\((\d+)\);?").unwrap());
+
+    let mut rc = None;
+    if let Some(caps) = re_rc_primary.captures_iter(&smcl_content).last() {
+        rc = caps.get(1).and_then(|m| m.as_str().parse::<i32>().ok());
+    }
+    if rc.is_none() {
+        if let Some(caps) = re_rc_secondary.captures_iter(&smcl_content).last() {
+            rc = caps.get(1).and_then(|m| m.as_str().parse::<i32>().ok());
+        }
     }
 
-    // Compile expression once
-    let parser = Parser::new();
-    let mut slab = Slab::new();
-    
-    let compiled = parser
-        .parse(&expr_str, &mut slab.ps)
-        .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("Parse error: {}", e)))?
-        .from(&slab.ps)
-        .compile(&slab.ps, &mut slab.cs);
+    let lines: Vec<&str> = smcl_content.lines().collect();
+    let (error_msg, context) = assemble_error_and_context(&lines, rc, rc_default);
 
-    // Validation
-    if names.len() != columns.len() || names.len() != is_string.len() {
-        return Err(pyo3::exceptions::PyValueError::new_err("Length mismatch"));
-    }
+    (error_msg, context, rc)
+}
 
-    // Pre-calculate name-to-index map for O(1) variable lookup
-    let name_map: std::collections::HashMap<&str, usize> = names
+// Chunk size for buffered reads in `fast_scan_log_from_file`. Large enough to amortize
+// syscall overhead, small enough to keep peak memory well under the size of the logs
+// this function exists to avoid loading in full.
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+// Held-back tail of each chunk, re-prepended to the next read, so a `{search r(N)...}`
+// tag or `r(N);` pattern split across a chunk boundary is always seen whole at least once.
+const STREAM_OVERLAP_BYTES: usize = 256;
+// Trailing line window kept in memory: comfortably more than the usual 30-line context
+// plus room for a multi-line `{err}` block, without holding the whole file.
+const STREAM_TAIL_WINDOW_LINES: usize = 512;
+
+// Find the byte offset to split `carry` at so that everything before it can be scanned
+// now while at least `overlap` trailing bytes (ending on a line boundary) are held back
+// for the next read. Returns 0 (process nothing yet) if no such line boundary exists.
+// Operates on raw bytes rather than `str` so the split always lands right after a `\n` —
+// `\n` is a single ASCII byte and never part of a multi-byte UTF-8 sequence, so the
+// prefix handed back is always a complete, boundary-safe chunk to decode, even if a
+// multi-byte character straddles the underlying read boundary.
+fn find_overlap_split(carry: &[u8], overlap: usize) -> usize {
+    carry
         .iter()
         .enumerate()
-        .map(|(i, name)| (name.as_str(), i))
-        .collect();
+        .rev()
+        .find(|&(i, &b)| b == b'\n' && carry.len() - (i + 1) >= overlap)
+        .map(|(i, _)| i + 1)
+        .unwrap_or(0)
+}
 
-    // Extract storage
-    let storage: Vec<Storage> = columns
-        .iter()
-        .zip(&is_string)
-        .map(|(obj, &is_str)| {
-            if is_str {
-                Ok(Storage::Txt(obj.extract(py)?))
-            } else {
-                Ok(Storage::Num(obj.extract(py)?))
-            }
-        })
-        .collect::<PyResult<_>>()?;
+// Core of `fast_scan_log_from_file`, kept free of PyO3 types so it can be unit-tested
+// directly instead of through the `#[pyfunction]` wrapper.
+fn fast_scan_log_from_file_core(
+    path: &str,
+    rc_default: i32,
+) -> Result<(String, String, Option<i32>), String> {
+    let re_rc_primary =
+        RE_RC_PRIMARY.get_or_init(|| Regex::new(r"\{search r\((\d+)\)").unwrap());
+    let re_rc_secondary =
+        RE_RC_SECONDARY.get_or_init(|| Regex::new(r"\br\((\d+)\);?").unwrap());
 
-    let row_count = if let Some(first) = storage.first() {
-        match first {
-            Storage::Num(arr) => arr.len()?,
-            Storage::Txt(vec) => vec.len(),
+    let file = File::open(path).map_err(|e| format!("failed to open {path}: {e}"))?;
+    let mut reader = BufReader::new(file);
+
+    let mut rc_primary: Option<i32> = None;
+    let mut rc_secondary: Option<i32> = None;
+    let mut tail_lines: VecDeque<String> = VecDeque::new();
+    let push_line = |line: &str, tail_lines: &mut VecDeque<String>| {
+        tail_lines.push_back(line.to_string());
+        if tail_lines.len() > STREAM_TAIL_WINDOW_LINES {
+            tail_lines.pop_front();
         }
-    } else {
-        0
     };
 
-    if row_count == 0 {
-        return Ok(Vec::new());
+    // Raw bytes, not a `String`: decoding each read independently with
+    // `from_utf8_lossy` would mangle a multi-byte UTF-8 character split across a chunk
+    // boundary. Carrying bytes and only decoding a prefix that ends right after a `\n`
+    // keeps every decode on a valid character boundary.
+    let mut carry: Vec<u8> = Vec::new();
+    let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+    loop {
+        let n = reader
+            .read(&mut buf)
+            .map_err(|e| format!("failed to read {path}: {e}"))?;
+        if n == 0 {
+            break;
+        }
+        carry.extend_from_slice(&buf[..n]);
+
+        let split_at = find_overlap_split(&carry, STREAM_OVERLAP_BYTES);
+        let process = String::from_utf8_lossy(&carry[..split_at]).into_owned();
+        if let Some(caps) = re_rc_primary.captures_iter(&process).last() {
+            rc_primary = caps.get(1).and_then(|m| m.as_str().parse::<i32>().ok());
+        }
+        if let Some(caps) = re_rc_secondary.captures_iter(&process).last() {
+            rc_secondary = caps.get(1).and_then(|m| m.as_str().parse::<i32>().ok());
+        }
+        for line in process.lines() {
+            push_line(line, &mut tail_lines);
+        }
+        carry.drain(..split_at);
+    }
+    if !carry.is_empty() {
+        let tail = String::from_utf8_lossy(&carry).into_owned();
+        if let Some(caps) = re_rc_primary.captures_iter(&tail).last() {
+            rc_primary = caps.get(1).and_then(|m| m.as_str().parse::<i32>().ok());
+        }
+        if let Some(caps) = re_rc_secondary.captures_iter(&tail).last() {
+            rc_secondary = caps.get(1).and_then(|m| m.as_str().parse::<i32>().ok());
+        }
+        for line in tail.lines() {
+            push_line(line, &mut tail_lines);
+        }
     }
 
-    let parsed: Vec<ColumnData> = storage
-        .iter()
-        .map(|s| match s {
-            Storage::Num(arr) => {
-                let slice = arr.as_slice()
-                    .map_err(|_| pyo3::exceptions::PyValueError::new_err("Non-contiguous"))?;
-                Ok(ColumnData::Numeric(slice))
-            }
-            Storage::Txt(vec) => Ok(ColumnData::Text(vec.as_slice())),
-        })
-        .collect::<PyResult<_>>()?;
+    let rc = rc_primary.or(rc_secondary);
+    let lines: Vec<&str> = tail_lines.iter().map(|s| s.as_str()).collect();
+    let (error_msg, context) = assemble_error_and_context(&lines, rc, rc_default);
+    Ok((error_msg, context, rc))
+}
 
-    // Parallel filtering with zero-copy variable lookup
-    let indices: Vec<usize> = if row_count >= PAR_FILTER_THRESHOLD {
-        (0..row_count)
-            .into_par_iter()
-            .filter_map(|i| {
-                // Callback for variable lookup - NO CLONES!
-                let mut cb = |name: &str, _args: Vec<f64>| -> Option<f64> {
-                    name_map.get(name).and_then(|&idx| {
-                        match &parsed[idx] {
-                            ColumnData::Numeric(slice) => {
-                                let val = slice[i];
-                                if val.is_nan() { None } else { Some(val) }
-                            }
-                            ColumnData::Text(slice) => {
-                                match &slice[i] {
-                                    Some(s) => s.parse::<f64>().ok(),
-                                    None => None,
-                                }
-                            }
-                        }
-                    })
-                };
+/// Streaming counterpart to `fast_scan_log` for session logs too large to read fully
+/// into memory: reads the file in fixed-size buffered chunks instead of taking an owned
+/// `String`, carries a small overlap between reads so a boundary-spanning tag or
+/// `r(N);` pattern is never missed, and keeps only a bounded trailing window of lines
+/// (enough to reconstruct the last `{err}` block plus the usual 30-line context) rather
+/// than materializing the whole file. Returns the same `(error_msg, context, rc)` shape
+/// as `fast_scan_log`. An `{err}` block that lies wholly before the last
+/// `STREAM_TAIL_WINDOW_LINES` lines of the file is not found; `error_msg` then falls back
+/// to `Stata error r(N)`.
+#[pyfunction]
+pub fn fast_scan_log_from_file(
+    path: String,
+    rc_default: i32,
+) -> PyResult<(String, String, Option<i32>)> {
+    fast_scan_log_from_file_core(&path, rc_default).map_err(pyo3::exceptions::PyValueError::new_err)
+}
 
-                match compiled.eval(&slab, &mut cb) {
-                    Ok(res) => if res != 0.0 && !res.is_nan() { Some(i) } else { None },
-                    Err(_) => None,
-                }
-            })
-            .collect()
-    } else {
-        // Sequential path
-        (0..row_count)
-            .filter(|&i| {
-                let mut cb = |name: &str, _args: Vec<f64>| -> Option<f64> {
-                    name_map.get(name).and_then(|&idx| {
-                        match &parsed[idx] {
-                            ColumnData::Numeric(slice) => {
-                                let val = slice[i];
-                                if val.is_nan() { None } else { Some(val) }
-                            }
-                            ColumnData::Text(slice) => {
-                                match &slice[i] {
-                                    Some(s) => s.parse::<f64>().ok(),
-                                    None => None,
-                                }
-                            }
-                        }
-                    })
-                };
+/// Richer sibling of `fast_scan_log` for do-files that run loops or several commands:
+/// instead of collapsing the log to one return code and one error block, walks the whole
+/// log once and returns every hit as a `(code, message, start_line, end_line, kind)`
+/// record — `"rc"` for each `r(N)` occurrence (search-tag matches still take priority
+/// over a bare `r(N);` on the same line, per the authority rule `fast_scan_log` uses),
+/// `"error"` for each `{err}` block (same backwards-contiguous block assembly and
+/// `{[^}]*}` stripping), and `"warning"`/`"note"` for lines that start with those labels
+/// once tags are stripped. Records come back in line order so callers can find the
+/// first failing command instead of only the last.
+#[pyfunction]
+pub fn fast_scan_log_structured(
+    smcl_content: String,
+) -> Vec<(Option<i32>, String, usize, usize, String)> {
+    let re_rc_primary =
+        RE_RC_PRIMARY.get_or_init(|| Regex::new(r"\{search r\((\d+)\)").unwrap());
+    let re_rc_secondary =
+        RE_RC_SECONDARY.get_or_init(|| Regex::new(r"\br\((\d+)\);?").unwrap());
+    let re_any_tag = RE_ANY_TAG.get_or_init(|| Regex::new(r"\{[^}]*\}").unwrap());
 
-                match compiled.eval(&slab, &mut cb) {
-                    Ok(res) => res != 0.0 && !res.is_nan(),
-                    Err(_) => false,
+    let lines: Vec<&str> = smcl_content.lines().collect();
+    let mut records: Vec<(Option<i32>, String, usize, usize, String)> = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        // Every search-tag hit on the line; only when there is none, every bare `r(N)` hit.
+        let mut hits: Vec<regex::Captures> = re_rc_primary.captures_iter(line).collect();
+        if hits.is_empty() {
+            hits = re_rc_secondary.captures_iter(line).collect();
+        }
+        for caps in hits {
+            let code = caps.get(1).and_then(|m| m.as_str().parse::<i32>().ok());
+            records.push((code, line.trim().to_string(), i + 1, i + 1, "rc".to_string()));
+        }
+
+        let cleaned = re_any_tag.replace_all(line, "").trim().to_string();
+        let lower = cleaned.to_ascii_lowercase();
+        if lower.starts_with("warning:") {
+            records.push((None, cleaned, i + 1, i + 1, "warning".to_string()));
+        } else if lower.starts_with("note:") {
+            records.push((None, cleaned, i + 1, i + 1, "note".to_string()));
+        }
+    }
+
+    // Group every run of contiguous `{err}` lines into its own block, the same assembly
+    // `fast_scan_log` does backwards from the last block, but applied to all of them.
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].contains("{err}") {
+            let start = i;
+            let mut err_lines = Vec::new();
+            while i < lines.len() && lines[i].contains("{err}") {
+                let cleaned = re_any_tag.replace_all(lines[i], "").trim().to_string();
+                if !cleaned.is_empty() {
+                    err_lines.push(cleaned);
                 }
-            })
-            .collect()
-    };
+                i += 1;
+            }
+            let end = i - 1;
+            if !err_lines.is_empty() {
+                records.push((None, err_lines.join(" "), start + 1, end + 1, "error".to_string()));
+            }
+        } else {
+            i += 1;
+        }
+    }
 
-    Ok(indices)
+    records.sort_by_key(|r| r.2);
+    records
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+// ---- Typed filter expression engine ------------------------------------
+//
+// fasteval only understands f64, so string predicates (`name == "CA"`,
+// `inlist(state, "CA", "NY")`, `id =~ "^US"`) used to be coerced through
+// `s.parse::<f64>()`, silently dropping every non-numeric string. The
+// types below are a small hand-rolled AST that evaluates directly against
+// `ColumnData`, so numeric and string predicates compose under `&&`/`||`
+// without any lossy float parsing.
 
-    #[test]
-    fn test_cmp_with_nulls_ordering() {
-        assert_eq!(cmp_with_nulls(1.0, 2.0, false, true), Ordering::Less);
-        assert_eq!(cmp_with_nulls(2.0, 1.0, false, true), Ordering::Greater);
-        assert_eq!(cmp_with_nulls(1.0, 2.0, true, true), Ordering::Greater);
-        assert_eq!(cmp_with_nulls(f64::NAN, 1.0, false, true), Ordering::Greater);
-        assert_eq!(cmp_with_nulls(f64::NAN, 1.0, false, false), Ordering::Less);
-        assert_eq!(cmp_with_nulls(f64::NAN, f64::NAN, false, true), Ordering::Equal);
-    }
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
 
-    #[test]
-    fn test_argsort_numeric_core() {
-        let col1 = [2.0, 1.0, 1.0, 2.0];
-        let col2 = [4.0, 3.0, 1.0, 2.0];
-        let arrays = vec![col1.as_slice(), col2.as_slice()];
-        
-        // Ascending col1, then descending col2
-        let res = argsort_numeric_core(&arrays, &[false, true], &[true, true]);
-        assert_eq!(res, vec![1, 2, 0, 3]);
-    }
+#[derive(Debug)]
+enum FilterExpr {
+    Num(f64),
+    Str(String),
+    Var(String),
+    Not(Box<FilterExpr>),
+    Neg(Box<FilterExpr>),
+    BinOp(BinOp, Box<FilterExpr>, Box<FilterExpr>),
+    Inlist(Box<FilterExpr>, Vec<FilterExpr>),
+    Regex(Box<FilterExpr>, Regex),
+    Missing(String),
+}
 
-    #[test]
-    fn test_smcl_to_markdown_comprehensive() {
+#[derive(Clone, Debug)]
+enum FilterValue {
+    Num(f64),
+    Str(String),
+}
+
+impl FilterValue {
+    fn truthy(&self) -> Result<bool, String> {
+        match self {
+            FilterValue::Num(n) => Ok(*n != 0.0 && !n.is_nan()),
+            FilterValue::Str(_) => Err("string value used in a boolean context".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Str(String),
+    Ident(String),
+    Sym(&'static str),
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '"' || c == '\'' {
+            let quote = c;
+            i += 1;
+            let mut s = String::new();
+            loop {
+                if i >= chars.len() {
+                    return Err("unterminated string literal".to_string());
+                }
+                let ch = chars[i];
+                if ch == '\\' && i + 1 < chars.len() {
+                    s.push(chars[i + 1]);
+                    i += 2;
+                    continue;
+                }
+                if ch == quote {
+                    i += 1;
+                    break;
+                }
+                s.push(ch);
+                i += 1;
+            }
+            tokens.push(Token::Str(s));
+            continue;
+        }
+        if c.is_ascii_digit() || (c == '.' && i + 1 < chars.len() && chars[i + 1].is_ascii_digit()) {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let n = text
+                .parse::<f64>()
+                .map_err(|_| format!("invalid number literal '{text}'"))?;
+            tokens.push(Token::Num(n));
+            continue;
+        }
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            continue;
+        }
+        let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+        if matches!(two.as_str(), "==" | "!=" | "<=" | ">=" | "&&" | "||" | "=~") {
+            let sym = match two.as_str() {
+                "==" => "==",
+                "!=" => "!=",
+                "<=" => "<=",
+                ">=" => ">=",
+                "&&" => "&&",
+                "||" => "||",
+                "=~" => "=~",
+                _ => unreachable!(),
+            };
+            tokens.push(Token::Sym(sym));
+            i += 2;
+            continue;
+        }
+        let sym = match c {
+            '(' => "(",
+            ')' => ")",
+            ',' => ",",
+            '+' => "+",
+            '-' => "-",
+            '*' => "*",
+            '/' => "/",
+            '<' => "<",
+            '>' => ">",
+            '!' => "!",
+            _ => return Err(format!("unexpected character '{c}' in filter expression")),
+        };
+        tokens.push(Token::Sym(sym));
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+struct ExprParser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> ExprParser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn bump(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn expect_sym(&mut self, sym: &str) -> Result<(), String> {
+        match self.bump() {
+            Some(Token::Sym(s)) if s == sym => Ok(()),
+            other => Err(format!("expected '{sym}', got {other:?}")),
+        }
+    }
+
+    // Precedence, loosest to tightest: || , && , ! , ==/!=/</<=/>/>=/=~ , +/- , * / , unary - , primary
+    fn parse_or(&mut self) -> Result<FilterExpr, String> {
+        let mut lhs = self.parse_and()?;
+        while matches!(self.peek(), Some(Token::Sym("||"))) {
+            self.bump();
+            let rhs = self.parse_and()?;
+            lhs = FilterExpr::BinOp(BinOp::Or, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<FilterExpr, String> {
+        let mut lhs = self.parse_not()?;
+        while matches!(self.peek(), Some(Token::Sym("&&"))) {
+            self.bump();
+            let rhs = self.parse_not()?;
+            lhs = FilterExpr::BinOp(BinOp::And, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_not(&mut self) -> Result<FilterExpr, String> {
+        if matches!(self.peek(), Some(Token::Sym("!"))) {
+            self.bump();
+            let inner = self.parse_not()?;
+            return Ok(FilterExpr::Not(Box::new(inner)));
+        }
+        self.parse_cmp()
+    }
+
+    fn parse_cmp(&mut self) -> Result<FilterExpr, String> {
+        let lhs = self.parse_add()?;
+        let op = match self.peek() {
+            Some(Token::Sym("==")) => Some(BinOp::Eq),
+            Some(Token::Sym("!=")) => Some(BinOp::Ne),
+            Some(Token::Sym("<")) => Some(BinOp::Lt),
+            Some(Token::Sym("<=")) => Some(BinOp::Le),
+            Some(Token::Sym(">")) => Some(BinOp::Gt),
+            Some(Token::Sym(">=")) => Some(BinOp::Ge),
+            Some(Token::Sym("=~")) => {
+                self.bump();
+                let pattern = match self.bump() {
+                    Some(Token::Str(s)) => s,
+                    other => {
+                        return Err(format!("=~ requires a string literal pattern, got {other:?}"))
+                    }
+                };
+                let re = Regex::new(&pattern)
+                    .map_err(|e| format!("invalid regex '{pattern}': {e}"))?;
+                return Ok(FilterExpr::Regex(Box::new(lhs), re));
+            }
+            _ => None,
+        };
+        match op {
+            Some(op) => {
+                self.bump();
+                let rhs = self.parse_add()?;
+                Ok(FilterExpr::BinOp(op, Box::new(lhs), Box::new(rhs)))
+            }
+            None => Ok(lhs),
+        }
+    }
+
+    fn parse_add(&mut self) -> Result<FilterExpr, String> {
+        let mut lhs = self.parse_mul()?;
+        loop {
+            match self.peek() {
+                Some(Token::Sym("+")) => {
+                    self.bump();
+                    let rhs = self.parse_mul()?;
+                    lhs = FilterExpr::BinOp(BinOp::Add, Box::new(lhs), Box::new(rhs));
+                }
+                Some(Token::Sym("-")) => {
+                    self.bump();
+                    let rhs = self.parse_mul()?;
+                    lhs = FilterExpr::BinOp(BinOp::Sub, Box::new(lhs), Box::new(rhs));
+                }
+                _ => break,
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn parse_mul(&mut self) -> Result<FilterExpr, String> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::Sym("*")) => {
+                    self.bump();
+                    let rhs = self.parse_unary()?;
+                    lhs = FilterExpr::BinOp(BinOp::Mul, Box::new(lhs), Box::new(rhs));
+                }
+                Some(Token::Sym("/")) => {
+                    self.bump();
+                    let rhs = self.parse_unary()?;
+                    lhs = FilterExpr::BinOp(BinOp::Div, Box::new(lhs), Box::new(rhs));
+                }
+                _ => break,
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<FilterExpr, String> {
+        if matches!(self.peek(), Some(Token::Sym("-"))) {
+            self.bump();
+            let inner = self.parse_unary()?;
+            return Ok(FilterExpr::Neg(Box::new(inner)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<FilterExpr, String> {
+        match self.bump() {
+            Some(Token::Num(n)) => Ok(FilterExpr::Num(n)),
+            Some(Token::Str(s)) => Ok(FilterExpr::Str(s)),
+            Some(Token::Sym("(")) => {
+                let inner = self.parse_or()?;
+                self.expect_sym(")")?;
+                Ok(inner)
+            }
+            Some(Token::Ident(name)) => {
+                if name.eq_ignore_ascii_case("inlist")
+                    && matches!(self.peek(), Some(Token::Sym("(")))
+                {
+                    self.bump();
+                    let target = self.parse_or()?;
+                    let mut options = Vec::new();
+                    while matches!(self.peek(), Some(Token::Sym(","))) {
+                        self.bump();
+                        options.push(self.parse_or()?);
+                    }
+                    self.expect_sym(")")?;
+                    if options.is_empty() {
+                        return Err("inlist() requires at least one candidate value".to_string());
+                    }
+                    Ok(FilterExpr::Inlist(Box::new(target), options))
+                } else if name.eq_ignore_ascii_case("missing")
+                    && matches!(self.peek(), Some(Token::Sym("(")))
+                {
+                    self.bump();
+                    let var_name = match self.bump() {
+                        Some(Token::Ident(n)) => n,
+                        other => {
+                            return Err(format!("missing() expects a variable name, got {other:?}"))
+                        }
+                    };
+                    self.expect_sym(")")?;
+                    Ok(FilterExpr::Missing(var_name))
+                } else {
+                    Ok(FilterExpr::Var(name))
+                }
+            }
+            other => Err(format!("unexpected token in filter expression: {other:?}")),
+        }
+    }
+}
+
+fn parse_filter_expr(src: &str) -> Result<FilterExpr, String> {
+    let tokens = tokenize(src)?;
+    let mut parser = ExprParser { tokens: &tokens, pos: 0 };
+    let expr = parser.parse_or()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(format!("unexpected trailing input at token {}", parser.pos));
+    }
+    Ok(expr)
+}
+
+fn lookup_filter_value(
+    name: &str,
+    row: usize,
+    parsed: &[ColumnData],
+    name_map: &std::collections::HashMap<&str, usize>,
+) -> Option<FilterValue> {
+    let idx = *name_map.get(name)?;
+    match &parsed[idx] {
+        ColumnData::Numeric(slice) => {
+            let v = slice[row];
+            if is_missing(v) {
+                None
+            } else {
+                Some(FilterValue::Num(v))
+            }
+        }
+        ColumnData::Text(slice) => slice[row].as_ref().map(|s| FilterValue::Str(s.clone())),
+    }
+}
+
+fn raw_is_missing(
+    name: &str,
+    row: usize,
+    parsed: &[ColumnData],
+    name_map: &std::collections::HashMap<&str, usize>,
+) -> Result<bool, String> {
+    let idx = *name_map
+        .get(name)
+        .ok_or_else(|| format!("unknown variable '{name}' in missing()"))?;
+    Ok(match &parsed[idx] {
+        ColumnData::Numeric(slice) => is_missing(slice[row]),
+        ColumnData::Text(slice) => slice[row].is_none(),
+    })
+}
+
+fn values_equal(a: &FilterValue, b: &FilterValue) -> Result<bool, String> {
+    match (a, b) {
+        (FilterValue::Num(x), FilterValue::Num(y)) => Ok(x == y),
+        (FilterValue::Str(x), FilterValue::Str(y)) => Ok(x == y),
+        _ => Err("cannot compare a string to a number".to_string()),
+    }
+}
+
+fn eval_filter_expr(
+    expr: &FilterExpr,
+    row: usize,
+    parsed: &[ColumnData],
+    name_map: &std::collections::HashMap<&str, usize>,
+) -> Result<FilterValue, String> {
+    match expr {
+        FilterExpr::Num(n) => Ok(FilterValue::Num(*n)),
+        FilterExpr::Str(s) => Ok(FilterValue::Str(s.clone())),
+        FilterExpr::Var(name) => lookup_filter_value(name, row, parsed, name_map)
+            .ok_or_else(|| format!("missing value for '{name}'")),
+        FilterExpr::Not(inner) => {
+            let v = eval_filter_expr(inner, row, parsed, name_map)?.truthy()?;
+            Ok(FilterValue::Num(if v { 0.0 } else { 1.0 }))
+        }
+        FilterExpr::Neg(inner) => match eval_filter_expr(inner, row, parsed, name_map)? {
+            FilterValue::Num(n) => Ok(FilterValue::Num(-n)),
+            FilterValue::Str(_) => Err("cannot negate a string value".to_string()),
+        },
+        FilterExpr::BinOp(BinOp::And, lhs, rhs) => {
+            if !eval_filter_expr(lhs, row, parsed, name_map)?.truthy()? {
+                return Ok(FilterValue::Num(0.0));
+            }
+            let r = eval_filter_expr(rhs, row, parsed, name_map)?.truthy()?;
+            Ok(FilterValue::Num(if r { 1.0 } else { 0.0 }))
+        }
+        FilterExpr::BinOp(BinOp::Or, lhs, rhs) => {
+            if eval_filter_expr(lhs, row, parsed, name_map)?.truthy()? {
+                return Ok(FilterValue::Num(1.0));
+            }
+            let r = eval_filter_expr(rhs, row, parsed, name_map)?.truthy()?;
+            Ok(FilterValue::Num(if r { 1.0 } else { 0.0 }))
+        }
+        FilterExpr::BinOp(BinOp::Eq, lhs, rhs) => {
+            let l = eval_filter_expr(lhs, row, parsed, name_map)?;
+            let r = eval_filter_expr(rhs, row, parsed, name_map)?;
+            Ok(FilterValue::Num(if values_equal(&l, &r)? { 1.0 } else { 0.0 }))
+        }
+        FilterExpr::BinOp(BinOp::Ne, lhs, rhs) => {
+            let l = eval_filter_expr(lhs, row, parsed, name_map)?;
+            let r = eval_filter_expr(rhs, row, parsed, name_map)?;
+            Ok(FilterValue::Num(if values_equal(&l, &r)? { 0.0 } else { 1.0 }))
+        }
+        FilterExpr::BinOp(op, lhs, rhs) => {
+            let l = eval_filter_expr(lhs, row, parsed, name_map)?;
+            let r = eval_filter_expr(rhs, row, parsed, name_map)?;
+            match (l, r) {
+                (FilterValue::Num(x), FilterValue::Num(y)) => Ok(FilterValue::Num(match op {
+                    BinOp::Add => x + y,
+                    BinOp::Sub => x - y,
+                    BinOp::Mul => x * y,
+                    BinOp::Div => x / y,
+                    BinOp::Lt => if x < y { 1.0 } else { 0.0 },
+                    BinOp::Le => if x <= y { 1.0 } else { 0.0 },
+                    BinOp::Gt => if x > y { 1.0 } else { 0.0 },
+                    BinOp::Ge => if x >= y { 1.0 } else { 0.0 },
+                    BinOp::Eq | BinOp::Ne | BinOp::And | BinOp::Or => unreachable!(),
+                })),
+                _ => Err("arithmetic/ordering comparisons require numeric operands".to_string()),
+            }
+        }
+        FilterExpr::Inlist(target, options) => {
+            let t = eval_filter_expr(target, row, parsed, name_map)?;
+            for opt in options {
+                let o = eval_filter_expr(opt, row, parsed, name_map)?;
+                if values_equal(&t, &o)? {
+                    return Ok(FilterValue::Num(1.0));
+                }
+            }
+            Ok(FilterValue::Num(0.0))
+        }
+        FilterExpr::Regex(target, re) => match eval_filter_expr(target, row, parsed, name_map)? {
+            FilterValue::Str(s) => Ok(FilterValue::Num(if re.is_match(&s) { 1.0 } else { 0.0 })),
+            FilterValue::Num(_) => Err("=~ requires a string operand".to_string()),
+        },
+        FilterExpr::Missing(name) => {
+            let missing = raw_is_missing(name, row, parsed, name_map)?;
+            Ok(FilterValue::Num(if missing { 1.0 } else { 0.0 }))
+        }
+    }
+}
+
+#[pyfunction]
+pub fn compute_filter_indices(
+    py: Python<'_>,
+    expr_str: String,
+    names: Vec<String>,
+    columns: Vec<Py<PyAny>>,
+    is_string: Vec<bool>,
+) -> PyResult<Vec<usize>> {
+    // Security: limit expression length
+    if expr_str.len() > MAX_FILTER_EXPR_LEN {
+        return Err(pyo3::exceptions::PyValueError::new_err("Filter expression too long"));
+    }
+
+    // Compile expression once
+    let expr = parse_filter_expr(&expr_str)
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("Parse error: {}", e)))?;
+
+    // Validation
+    if names.len() != columns.len() || names.len() != is_string.len() {
+        return Err(pyo3::exceptions::PyValueError::new_err("Length mismatch"));
+    }
+
+    // Pre-calculate name-to-index map for O(1) variable lookup
+    let name_map: std::collections::HashMap<&str, usize> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect();
+
+    // Extract storage
+    let storage: Vec<Storage> = columns
+        .iter()
+        .zip(&is_string)
+        .map(|(obj, &is_str)| {
+            if is_str {
+                Ok(Storage::Txt(obj.extract(py)?))
+            } else {
+                Ok(Storage::Num(obj.extract(py)?))
+            }
+        })
+        .collect::<PyResult<_>>()?;
+
+    let row_count = if let Some(first) = storage.first() {
+        match first {
+            Storage::Num(arr) => arr.len()?,
+            Storage::Txt(vec) => vec.len(),
+        }
+    } else {
+        0
+    };
+
+    if row_count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let parsed: Vec<ColumnData> = storage
+        .iter()
+        .map(|s| match s {
+            Storage::Num(arr) => {
+                let slice = arr.as_slice()
+                    .map_err(|_| pyo3::exceptions::PyValueError::new_err("Non-contiguous"))?;
+                Ok(ColumnData::Numeric(slice))
+            }
+            Storage::Txt(vec) => Ok(ColumnData::Text(vec.as_slice())),
+        })
+        .collect::<PyResult<_>>()?;
+
+    // Row predicate: evaluation errors (type mismatch, missing value, ...) exclude the row,
+    // matching the previous fasteval behavior of treating eval failures as non-matches.
+    let eval_row = |i: usize| -> bool {
+        match eval_filter_expr(&expr, i, &parsed, &name_map) {
+            Ok(v) => v.truthy().unwrap_or(false),
+            Err(_) => false,
+        }
+    };
+
+    let indices: Vec<usize> = if row_count >= PAR_FILTER_THRESHOLD {
+        (0..row_count).into_par_iter().filter(|&i| eval_row(i)).collect()
+    } else {
+        (0..row_count).filter(|&i| eval_row(i)).collect()
+    };
+
+    Ok(indices)
+}
+
+/// Builds the sentinel `f64` the Rust side recognizes as a Stata numeric missing
+/// value, so the Python side can construct `.`/`.a`.../`.z` columns that sort and
+/// filter correctly through `argsort_numeric`, `argsort_mixed`, and
+/// `compute_filter_indices`. `tag` is `None` for `.`, or `'a'..='z'` (case-insensitive)
+/// for `.a`..`.z`.
+#[pyfunction]
+pub fn stata_missing_value(tag: Option<char>) -> PyResult<f64> {
+    let idx = match tag {
+        None => 0u8,
+        Some(c) if c.is_ascii_alphabetic() => c.to_ascii_lowercase() as u8 - b'a' + 1,
+        Some(c) => {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "invalid Stata missing-value tag '{c}'"
+            )))
+        }
+    };
+    Ok(missing_sentinel(idx))
+}
+
+// ---- Native group-by aggregation (collapse/egen equivalent) ------------
+//
+// `group_aggregate` moves Stata `collapse`/`egen` workloads off the Python path:
+// sort the rows on the grouping columns with the same `argsort_mixed_core` used
+// for `argsort_mixed`, scan the sorted order for contiguous runs of equal keys,
+// and compute each requested statistic over a run. `median` sorts the run's values and
+// `sd` makes a second pass over them for the variance, so not every statistic is
+// computed in a single pass.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum GroupStat {
+    Count,
+    Sum,
+    Mean,
+    Min,
+    Max,
+    Median,
+    Sd,
+    First,
+    Last,
+}
+
+impl GroupStat {
+    fn parse(s: &str) -> Result<GroupStat, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "count" => Ok(GroupStat::Count),
+            "sum" => Ok(GroupStat::Sum),
+            "mean" => Ok(GroupStat::Mean),
+            "min" => Ok(GroupStat::Min),
+            "max" => Ok(GroupStat::Max),
+            "median" => Ok(GroupStat::Median),
+            "sd" => Ok(GroupStat::Sd),
+            "first" => Ok(GroupStat::First),
+            "last" => Ok(GroupStat::Last),
+            other => Err(format!("unknown aggregation statistic '{other}'")),
+        }
+    }
+}
+
+fn median_of(sorted_clean: &mut [f64]) -> f64 {
+    if sorted_clean.is_empty() {
+        return f64::NAN;
+    }
+    sorted_clean.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    let n = sorted_clean.len();
+    if n % 2 == 1 {
+        sorted_clean[n / 2]
+    } else {
+        (sorted_clean[n / 2 - 1] + sorted_clean[n / 2]) / 2.0
+    }
+}
+
+fn sample_sd(clean: &[f64]) -> f64 {
+    let n = clean.len();
+    if n < 2 {
+        return f64::NAN;
+    }
+    let mean = clean.iter().sum::<f64>() / n as f64;
+    let var = clean.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n as f64 - 1.0);
+    var.sqrt()
+}
+
+// `values` are the target-column values for one group, in sort order; missing values
+// (Stata sysmiss/extended-missing, see `is_missing`) are excluded from every statistic
+// except `first`/`last`, which take whatever value actually sits at that end of the run
+// -- matching Stata's `collapse`.
+fn compute_group_stat(values: &[f64], stat: GroupStat) -> f64 {
+    match stat {
+        GroupStat::First => *values.first().unwrap_or(&f64::NAN),
+        GroupStat::Last => *values.last().unwrap_or(&f64::NAN),
+        _ => {
+            let mut clean: Vec<f64> = values.iter().copied().filter(|v| !is_missing(*v)).collect();
+            match stat {
+                GroupStat::Count => clean.len() as f64,
+                GroupStat::Sum => clean.iter().sum(),
+                GroupStat::Mean => {
+                    if clean.is_empty() {
+                        f64::NAN
+                    } else {
+                        clean.iter().sum::<f64>() / clean.len() as f64
+                    }
+                }
+                GroupStat::Min => clean.iter().copied().reduce(f64::min).unwrap_or(f64::NAN),
+                GroupStat::Max => clean.iter().copied().reduce(f64::max).unwrap_or(f64::NAN),
+                GroupStat::Median => median_of(&mut clean),
+                GroupStat::Sd => sample_sd(&clean),
+                GroupStat::First | GroupStat::Last => unreachable!("handled above"),
+            }
+        }
+    }
+}
+
+// Two rows belong to the same group iff every key column agrees; distinct missing
+// codes (e.g. `.` vs `.a`) are treated as distinct group keys, same as a real value.
+fn group_keys_eq(parsed: &[ColumnData], i: usize, j: usize) -> bool {
+    parsed.iter().all(|col| match col {
+        ColumnData::Numeric(slice) => {
+            let (a, b) = (slice[i], slice[j]);
+            match (missing_tag(a), missing_tag(b)) {
+                (Some(ta), Some(tb)) => ta == tb,
+                (None, None) => a == b,
+                _ => false,
+            }
+        }
+        ColumnData::Text(slice) => slice[i] == slice[j],
+    })
+}
+
+fn group_aggregate_core(
+    parsed_keys: &[ColumnData],
+    target_slices: &[&[f64]],
+    stats: &[GroupStat],
+) -> (Vec<usize>, Vec<Vec<f64>>) {
+    let row_count = match parsed_keys.first() {
+        Some(ColumnData::Numeric(values)) => values.len(),
+        Some(ColumnData::Text(values)) => values.len(),
+        None => 0,
+    };
+    if row_count == 0 {
+        return (Vec::new(), vec![Vec::new(); target_slices.len()]);
+    }
+
+    // Sort on the group keys (ascending, nulls last) so each group's rows become contiguous.
+    let descending = vec![false; parsed_keys.len()];
+    let nulls_last = vec![true; parsed_keys.len()];
+    let order = argsort_mixed_core(parsed_keys, &descending, &nulls_last);
+
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    let mut start = 0;
+    for i in 1..order.len() {
+        if !group_keys_eq(parsed_keys, order[i - 1], order[i]) {
+            runs.push((start, i));
+            start = i;
+        }
+    }
+    runs.push((start, order.len()));
+
+    let compute_run = |&(s, e): &(usize, usize)| -> Vec<f64> {
+        let idxs = &order[s..e];
+        target_slices
+            .iter()
+            .zip(stats)
+            .map(|(slice, &stat)| {
+                let values: Vec<f64> = idxs.iter().map(|&i| slice[i]).collect();
+                compute_group_stat(&values, stat)
+            })
+            .collect()
+    };
+
+    let group_rows: Vec<Vec<f64>> = if runs.len() >= PAR_GROUP_THRESHOLD {
+        runs.par_iter().map(compute_run).collect()
+    } else {
+        runs.iter().map(compute_run).collect()
+    };
+
+    // Transpose group-major rows into the target-major columns Python expects.
+    let mut columns: Vec<Vec<f64>> = vec![Vec::with_capacity(runs.len()); target_slices.len()];
+    for row in &group_rows {
+        for (col, &v) in columns.iter_mut().zip(row.iter()) {
+            col.push(v);
+        }
+    }
+
+    let representative_rows = runs.iter().map(|&(s, _)| order[s]).collect();
+
+    (representative_rows, columns)
+}
+
+#[pyfunction]
+pub fn group_aggregate(
+    py: Python<'_>,
+    group_columns: Vec<Py<PyAny>>,
+    group_is_string: Vec<bool>,
+    target_columns: Vec<PyReadonlyArray1<f64>>,
+    stats: Vec<String>,
+) -> PyResult<(Vec<usize>, Vec<Vec<f64>>)> {
+    if group_columns.is_empty() {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "group_aggregate requires at least one grouping column",
+        ));
+    }
+    if group_columns.len() != group_is_string.len() {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "group column/is_string length mismatch",
+        ));
+    }
+    if target_columns.len() != stats.len() {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "target columns/stats length mismatch",
+        ));
+    }
+
+    let parsed_stats: Vec<GroupStat> = stats
+        .iter()
+        .map(|s| GroupStat::parse(s))
+        .collect::<Result<_, _>>()
+        .map_err(pyo3::exceptions::PyValueError::new_err)?;
+
+    // Extract group-key storage (same zero-copy pattern as argsort_mixed).
+    let key_storage: Vec<Storage> = group_columns
+        .iter()
+        .zip(&group_is_string)
+        .map(|(obj, &is_str)| {
+            if is_str {
+                Ok(Storage::Txt(obj.extract(py)?))
+            } else {
+                Ok(Storage::Num(obj.extract(py)?))
+            }
+        })
+        .collect::<PyResult<_>>()?;
+
+    let mut row_count: Option<usize> = None;
+    let parsed_keys: Vec<ColumnData> = key_storage
+        .iter()
+        .map(|s| match s {
+            Storage::Num(arr) => {
+                let slice = arr
+                    .as_slice()
+                    .map_err(|_| pyo3::exceptions::PyValueError::new_err("non-contiguous group column"))?;
+                let len = slice.len();
+                match row_count {
+                    Some(n) if n != len => {
+                        return Err(pyo3::exceptions::PyValueError::new_err("group column length mismatch"))
+                    }
+                    _ => row_count = Some(len),
+                }
+                Ok(ColumnData::Numeric(slice))
+            }
+            Storage::Txt(vec) => {
+                let len = vec.len();
+                match row_count {
+                    Some(n) if n != len => {
+                        return Err(pyo3::exceptions::PyValueError::new_err("group column length mismatch"))
+                    }
+                    _ => row_count = Some(len),
+                }
+                Ok(ColumnData::Text(vec.as_slice()))
+            }
+        })
+        .collect::<PyResult<_>>()?;
+
+    let rows = row_count.unwrap_or(0);
+    if rows == 0 {
+        return Ok((Vec::new(), vec![Vec::new(); target_columns.len()]));
+    }
+
+    for t in &target_columns {
+        if t.len()? != rows {
+            return Err(pyo3::exceptions::PyValueError::new_err("target column length mismatch"));
+        }
+    }
+    let target_slices: Vec<&[f64]> = target_columns
+        .iter()
+        .map(|t| {
+            t.as_slice()
+                .map_err(|_| pyo3::exceptions::PyValueError::new_err("non-contiguous target column"))
+        })
+        .collect::<PyResult<_>>()?;
+
+    Ok(group_aggregate_core(&parsed_keys, &target_slices, &parsed_stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cmp_with_nulls_ordering() {
+        assert_eq!(cmp_with_nulls(1.0, 2.0, false, true), Ordering::Less);
+        assert_eq!(cmp_with_nulls(2.0, 1.0, false, true), Ordering::Greater);
+        assert_eq!(cmp_with_nulls(1.0, 2.0, true, true), Ordering::Greater);
+        assert_eq!(cmp_with_nulls(f64::NAN, 1.0, false, true), Ordering::Greater);
+        assert_eq!(cmp_with_nulls(f64::NAN, 1.0, false, false), Ordering::Less);
+        assert_eq!(cmp_with_nulls(f64::NAN, f64::NAN, false, true), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_argsort_numeric_core() {
+        let col1 = [2.0, 1.0, 1.0, 2.0];
+        let col2 = [4.0, 3.0, 1.0, 2.0];
+        let arrays = vec![col1.as_slice(), col2.as_slice()];
+        
+        // Ascending col1, then descending col2
+        let res = argsort_numeric_core(&arrays, &[false, true], &[true, true]);
+        assert_eq!(res, vec![1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn test_smcl_to_markdown_comprehensive() {
         let smcl = vec![
             "{smcl}",
             "{title:Full Documentation}",
@@ -574,6 +1672,53 @@ mod tests {
         assert!(!md.contains("{pstd}"));
     }
 
+    #[test]
+    fn test_smcl_to_markdown_structured_synopt_table() {
+        let smcl = vec![
+            "{smcl}",
+            "{title:Options}",
+            "{dlgtab:Main}",
+            "{synoptset 20 tabbed}",
+            "{synopthdr}",
+            "{hline}",
+            "{synopt:{bf:noconstant}}suppress constant term{p_end}",
+            "{synopt:robust}use {bf:robust} standard errors{p_end}",
+            "{hline}",
+        ].join("\n");
+
+        let md = smcl_to_markdown_structured(smcl);
+
+        assert!(md.contains("## Options"));
+        assert!(md.contains("### Main"));
+        assert!(md.contains("| **noconstant** | suppress constant term |"));
+        assert!(md.contains("| robust | use **robust** standard errors |"));
+        // A markdown table separator row should follow the header.
+        assert!(md.contains("| --- | --- |"));
+        // No raw synopt/hline/dlgtab tags should leak through.
+        assert!(!md.contains("{synopt"));
+        assert!(!md.contains("{hline}"));
+        assert!(!md.contains("{dlgtab"));
+    }
+
+    #[test]
+    fn test_smcl_to_markdown_structured_col_table() {
+        let smcl = vec![
+            "{smcl}",
+            "{col 5}Variable{col 20}Coef.{col 35}Std. Err.",
+            "{hline}",
+            "{col 5}price{col 20}-0.05{col 35}0.01",
+            "{col 5}mpg{col 20}1.23{col 35}0.45",
+            "{hline}",
+        ].join("\n");
+
+        let md = smcl_to_markdown_structured(smcl);
+
+        assert!(md.contains("| Variable | Coef. | Std. Err. |"));
+        assert!(md.contains("| price | -0.05 | 0.01 |"));
+        assert!(md.contains("| mpg | 1.23 | 0.45 |"));
+        assert!(md.contains("| --- | --- | --- |"));
+    }
+
     #[test]
     fn test_fast_scan_log_comprehensive() {
         // Multi-line error + return code boundary check
@@ -619,20 +1764,332 @@ mod tests {
     }
 
     #[test]
-    fn test_fasteval_logic_unit() {
-        let parser = fasteval::Parser::new();
-        let mut slab = Slab::new();
-        let compiled = parser.parse("(x > 10) && (y < 5)", &mut slab.ps).unwrap()
-            .from(&slab.ps).compile(&slab.ps, &mut slab.cs);
-            
-        let mut cb = |name: &str, _: Vec<f64>| -> Option<f64> {
-            match name {
-                "x" => Some(15.0),
-                "y" => Some(2.0),
-                _ => None
-            }
+    fn test_fast_scan_log_structured_multi_error() {
+        // Two separate failing commands in a loop, each with its own {err} block and
+        // return code, plus a note that should surface as its own record.
+        let smcl = vec![
+            "{txt}note: variable price already defined",
+            "{err}variable price not found",
+            "{err}on line 1 of do-file",
+            "r(111);",
+            "{txt}continuing loop...",
+            "{err}matrix has wrong dimension",
+            "{search r(503):r(503);}",
+        ]
+        .join("\n");
+
+        let records = fast_scan_log_structured(smcl);
+
+        let notes: Vec<_> = records.iter().filter(|r| r.4 == "note").collect();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].1, "note: variable price already defined");
+
+        let errors: Vec<_> = records.iter().filter(|r| r.4 == "error").collect();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].1, "variable price not found on line 1 of do-file");
+        assert_eq!(errors[1].1, "matrix has wrong dimension");
+
+        let rcs: Vec<_> = records.iter().filter(|r| r.4 == "rc").collect();
+        assert_eq!(rcs.len(), 2);
+        assert_eq!(rcs[0].0, Some(111));
+        assert_eq!(rcs[1].0, Some(503));
+
+        // Records come back in line order, the first failing command before the second.
+        assert!(errors[0].2 < errors[1].2);
+    }
+
+    #[test]
+    fn test_fast_scan_log_from_file_matches_in_memory() {
+        let smcl = vec![
+            "Some preamble text",
+            "{err}variable price not found",
+            "{err}on line 42 of do-file",
+            "{txt}Checking... Check:r(456);",
+            "{search r(111):r(111);}",
+        ]
+        .join("\n");
+
+        let mut path = std::env::temp_dir();
+        path.push(format!("fast_scan_log_test_{:?}.smcl", std::thread::current().id()));
+        std::fs::write(&path, &smcl).unwrap();
+
+        let (msg, context, rc) =
+            fast_scan_log_from_file_core(&path.to_string_lossy(), 0).unwrap();
+        let (expected_msg, _, expected_rc) = fast_scan_log(smcl, 0);
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(rc, expected_rc);
+        assert_eq!(msg, expected_msg);
+        assert!(context.contains("variable price not found"));
+        assert!(context.contains("{search r(111)"));
+    }
+
+    #[test]
+    fn test_fast_scan_log_from_file_chunk_boundary_split() {
+        // Force the `{search r(...)}` tag to straddle a chunk boundary so the overlap
+        // handling is actually exercised rather than happening to land on one read.
+        let padding = "x".repeat(STREAM_CHUNK_SIZE - 20);
+        let smcl = format!("{padding}\n{{err}}boom\n{{search r(198):r(198);}}");
+
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "fast_scan_log_boundary_test_{:?}.smcl",
+            std::thread::current().id()
+        ));
+        std::fs::write(&path, &smcl).unwrap();
+
+        let (msg, _, rc) = fast_scan_log_from_file_core(&path.to_string_lossy(), 0).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(rc, Some(198));
+        assert_eq!(msg, "boom");
+    }
+
+    #[test]
+    fn test_fast_scan_log_from_file_utf8_char_split_across_chunk_boundary() {
+        // Place the 2-byte UTF-8 character "é" (0xC3 0xA9) so its first byte is the last
+        // byte of the first chunk read and its continuation byte is the first byte of
+        // the next: a naive `from_utf8_lossy` per chunk would mangle it into replacement
+        // characters on both sides.
+        let padding = "x".repeat(STREAM_CHUNK_SIZE - 1);
+        let smcl = format!("{padding}é\n{{err}}boom\n{{search r(203):r(203);}}");
+
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "fast_scan_log_utf8_split_test_{:?}.smcl",
+            std::thread::current().id()
+        ));
+        std::fs::write(&path, &smcl).unwrap();
+
+        let (msg, context, rc) = fast_scan_log_from_file_core(&path.to_string_lossy(), 0).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(rc, Some(203));
+        assert_eq!(msg, "boom");
+        assert!(context.contains('é'));
+        assert!(!context.contains('\u{FFFD}'));
+    }
+
+    #[test]
+    fn test_fast_scan_log_from_file_missing_path_errors() {
+        let result = fast_scan_log_from_file_core("/no/such/file/here.smcl", 0);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_numeric_filter_predicates_comprehensive() {
+        let x = [15.0, 5.0, 15.0];
+        let y = [2.0, 2.0, 8.0];
+        let cols = vec![ColumnData::Numeric(&x), ColumnData::Numeric(&y)];
+        let names = ["x", "y"];
+
+        // Logical AND over two numeric comparisons, the same predicate the old
+        // fasteval-based engine was tested against before the rewrite to a typed AST.
+        assert!(eval_filter_str("(x > 10) && (y < 5)", &names, &cols, 0).unwrap());
+        assert!(!eval_filter_str("(x > 10) && (y < 5)", &names, &cols, 1).unwrap());
+        assert!(!eval_filter_str("(x > 10) && (y < 5)", &names, &cols, 2).unwrap());
+
+        // Arithmetic and OR.
+        assert!(eval_filter_str("x - y > 10 || y == 8", &names, &cols, 0).unwrap());
+        assert!(eval_filter_str("x - y > 10 || y == 8", &names, &cols, 2).unwrap());
+        assert!(!eval_filter_str("x - y > 10 || y == 8", &names, &cols, 1).unwrap());
+    }
+
+    fn eval_filter_str(
+        expr_str: &str,
+        names: &[&str],
+        parsed: &[ColumnData],
+        row: usize,
+    ) -> Result<bool, String> {
+        let expr = parse_filter_expr(expr_str)?;
+        let name_map: std::collections::HashMap<&str, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| (n, i))
+            .collect();
+        eval_filter_expr(&expr, row, parsed, &name_map)?.truthy()
+    }
+
+    #[test]
+    fn test_string_filter_predicates_comprehensive() {
+        let age = [25.0, 30.0, 17.0];
+        let state = [
+            Some("CA".to_string()),
+            Some("NY".to_string()),
+            Some("CA".to_string()),
+        ];
+        let id = [Some("US-1".to_string()), Some("CA-2".to_string()), None];
+        let cols = vec![
+            ColumnData::Numeric(&age),
+            ColumnData::Text(&state),
+            ColumnData::Text(&id),
+        ];
+        let names = ["age", "state", "id"];
+
+        // String equality combined with a numeric predicate via &&
+        assert!(eval_filter_str(r#"state == "CA" && age >= 18"#, &names, &cols, 0).unwrap());
+        assert!(!eval_filter_str(r#"state == "CA" && age >= 18"#, &names, &cols, 2).unwrap());
+        assert!(!eval_filter_str(r#"state == "CA" && age >= 18"#, &names, &cols, 1).unwrap());
+
+        // inlist() over string candidates
+        assert!(eval_filter_str(r#"inlist(state, "CA", "TX")"#, &names, &cols, 0).unwrap());
+        assert!(!eval_filter_str(r#"inlist(state, "CA", "TX")"#, &names, &cols, 1).unwrap());
+
+        // Regex match operator
+        assert!(eval_filter_str(r#"id =~ "^US""#, &names, &cols, 0).unwrap());
+        assert!(!eval_filter_str(r#"id =~ "^US""#, &names, &cols, 1).unwrap());
+        // A missing value surfaces as an eval error; callers (compute_filter_indices)
+        // treat that as "row excluded" rather than propagating a hard failure.
+        assert!(eval_filter_str(r#"id =~ "^US""#, &names, &cols, 2).is_err());
+
+        // Mixing string and numeric operands directly is a type error, not a silent float parse.
+        assert!(eval_filter_str(r#"state == age"#, &names, &cols, 0).is_err());
+    }
+
+    #[test]
+    fn test_extended_missing_ordering() {
+        let dot = missing_sentinel(0);
+        let dot_a = missing_sentinel(1);
+        let dot_z = missing_sentinel(26);
+
+        // `.` < `.a` < ... < `.z`, and all of them sort above every real number.
+        assert_eq!(cmp_with_nulls(dot, dot_a, false, true), Ordering::Less);
+        assert_eq!(cmp_with_nulls(dot_a, dot_z, false, true), Ordering::Less);
+        assert_eq!(cmp_with_nulls(1.0e9, dot, false, true), Ordering::Less);
+
+        // nulls_last only controls block placement, not the intra-missing order.
+        assert_eq!(cmp_with_nulls(dot, dot_a, false, false), Ordering::Less);
+        assert_eq!(cmp_with_nulls(dot, 5.0, false, false), Ordering::Less);
+
+        // descending reverses both real values and the missing-vs-missing order.
+        assert_eq!(cmp_with_nulls(dot, dot_a, true, true), Ordering::Greater);
+
+        // Plain NaN is the generic sysmiss, same rank as `.`.
+        assert_eq!(cmp_with_nulls(f64::NAN, dot, false, true), Ordering::Equal);
+
+        assert_eq!(missing_tag(dot_z), Some(26));
+        assert_eq!(missing_tag(42.0), None);
+    }
+
+    #[test]
+    fn test_argsort_numeric_core_extended_missing() {
+        let dot = missing_sentinel(0);
+        let dot_b = missing_sentinel(2);
+        let col = [5.0, dot_b, f64::NAN, dot, 1.0];
+        let arrays = vec![col.as_slice()];
+
+        // Ascending, nulls last: reals first (1, 5), then the two `.`-rank entries
+        // (NaN and the sentinel tie and may appear in either order), then `.b` last.
+        let res = argsort_numeric_core(&arrays, &[false], &[true]);
+        assert_eq!(&res[0..2], &[4, 0]);
+        assert_eq!(res[4], 1);
+        assert!(res[2..4].contains(&2) && res[2..4].contains(&3));
+    }
+
+    #[test]
+    fn test_filter_missing_predicate() {
+        let income = [1000.0, f64::NAN, missing_sentinel(1), 500.0];
+        let cols = vec![ColumnData::Numeric(&income)];
+        let names = ["income"];
+
+        assert!(!eval_filter_str("missing(income)", &names, &cols, 0).unwrap());
+        assert!(eval_filter_str("missing(income)", &names, &cols, 1).unwrap());
+        assert!(eval_filter_str("missing(income)", &names, &cols, 2).unwrap());
+        assert!(eval_filter_str("!missing(income) && income > 600", &names, &cols, 0).unwrap());
+        assert!(!eval_filter_str("!missing(income) && income > 600", &names, &cols, 3).unwrap());
+    }
+
+    #[test]
+    fn test_compute_group_stat() {
+        assert_eq!(compute_group_stat(&[1.0, 2.0, 3.0], GroupStat::Count), 3.0);
+        assert_eq!(compute_group_stat(&[1.0, 2.0, 3.0], GroupStat::Sum), 6.0);
+        assert_eq!(compute_group_stat(&[1.0, 2.0, 3.0], GroupStat::Mean), 2.0);
+        assert_eq!(compute_group_stat(&[1.0, 2.0, 3.0], GroupStat::Min), 1.0);
+        assert_eq!(compute_group_stat(&[1.0, 2.0, 3.0], GroupStat::Max), 3.0);
+        assert_eq!(compute_group_stat(&[1.0, 2.0, 3.0, 4.0], GroupStat::Median), 2.5);
+        assert_eq!(compute_group_stat(&[1.0, 2.0, 3.0], GroupStat::First), 1.0);
+        assert_eq!(compute_group_stat(&[1.0, 2.0, 3.0], GroupStat::Last), 3.0);
+        assert!((compute_group_stat(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0], GroupStat::Sd) - 2.13809).abs() < 1e-4);
+
+        // Missing values are excluded from every stat except first/last.
+        let dot = missing_sentinel(0);
+        assert_eq!(compute_group_stat(&[dot, 10.0, f64::NAN, 20.0], GroupStat::Count), 2.0);
+        assert_eq!(compute_group_stat(&[dot, 10.0, f64::NAN, 20.0], GroupStat::Mean), 15.0);
+        assert_eq!(compute_group_stat(&[dot, 10.0, f64::NAN, 20.0], GroupStat::First), dot);
+
+        // A single observation has no sample standard deviation.
+        assert!(compute_group_stat(&[5.0], GroupStat::Sd).is_nan());
+        assert_eq!(compute_group_stat(&[], GroupStat::Count), 0.0);
+        assert!(compute_group_stat(&[], GroupStat::Mean).is_nan());
+    }
+
+    #[test]
+    fn test_group_aggregate_core() {
+        // group keys: region (string), year (numeric); one group has a missing sales value.
+        let region = [
+            Some("east".to_string()),
+            Some("east".to_string()),
+            Some("west".to_string()),
+            Some("west".to_string()),
+        ];
+        let year = [2020.0, 2020.0, 2020.0, 2021.0];
+        let sales = [100.0, 200.0, f64::NAN, 50.0];
+        let units = [1.0, 2.0, 3.0, 4.0];
+
+        let keys = vec![ColumnData::Text(&region), ColumnData::Numeric(&year)];
+        let targets: Vec<&[f64]> = vec![&sales, &units];
+        let stats = [GroupStat::Sum, GroupStat::Count];
+
+        let (representatives, columns) = group_aggregate_core(&keys, &targets, &stats);
+
+        // Three distinct (region, year) groups: (east, 2020), (west, 2020), (west, 2021).
+        assert_eq!(representatives.len(), 3);
+        assert_eq!(columns.len(), 2);
+
+        let find_group = |region_val: &str, year_val: f64| -> usize {
+            representatives
+                .iter()
+                .position(|&row| {
+                    region[row].as_deref() == Some(region_val) && year[row] == year_val
+                })
+                .unwrap()
         };
-        assert!(compiled.eval(&slab, &mut cb).unwrap() != 0.0);
+
+        let east_2020 = find_group("east", 2020.0);
+        assert_eq!(columns[0][east_2020], 300.0); // sum(sales) = 100 + 200
+        assert_eq!(columns[1][east_2020], 2.0); // count(units) = 2
+
+        let west_2020 = find_group("west", 2020.0);
+        assert_eq!(columns[0][west_2020], 0.0); // sum(sales) over a single missing value
+        assert_eq!(columns[1][west_2020], 1.0); // count(units), sales missing doesn't affect units
+
+        let west_2021 = find_group("west", 2021.0);
+        assert_eq!(columns[0][west_2021], 50.0);
+        assert_eq!(columns[1][west_2021], 1.0);
+    }
+
+    #[test]
+    fn test_group_aggregate_core_first_last_is_dataset_order() {
+        // All rows share one group key, so first/last only have a well-defined answer if
+        // the group sort is stable and preserves the original row order for ties.
+        let region = [
+            Some("east".to_string()),
+            Some("east".to_string()),
+            Some("east".to_string()),
+            Some("east".to_string()),
+        ];
+        let sales = [10.0, 20.0, 30.0, 40.0];
+
+        let keys = vec![ColumnData::Text(&region)];
+        let targets: Vec<&[f64]> = vec![&sales];
+
+        let (representatives, columns) = group_aggregate_core(&keys, &targets, &[GroupStat::First]);
+        assert_eq!(representatives.len(), 1);
+        assert_eq!(columns[0][0], 10.0);
+
+        let (_, columns_last) = group_aggregate_core(&keys, &targets, &[GroupStat::Last]);
+        assert_eq!(columns_last[0][0], 40.0);
     }
 }
 
@@ -641,7 +2098,12 @@ fn _native_ops(_py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(argsort_numeric, m)?)?;
     m.add_function(wrap_pyfunction!(argsort_mixed, m)?)?;
     m.add_function(wrap_pyfunction!(smcl_to_markdown, m)?)?;
+    m.add_function(wrap_pyfunction!(smcl_to_markdown_structured, m)?)?;
     m.add_function(wrap_pyfunction!(fast_scan_log, m)?)?;
+    m.add_function(wrap_pyfunction!(fast_scan_log_structured, m)?)?;
+    m.add_function(wrap_pyfunction!(fast_scan_log_from_file, m)?)?;
     m.add_function(wrap_pyfunction!(compute_filter_indices, m)?)?;
+    m.add_function(wrap_pyfunction!(stata_missing_value, m)?)?;
+    m.add_function(wrap_pyfunction!(group_aggregate, m)?)?;
     Ok(())
 }
\ No newline at end of file